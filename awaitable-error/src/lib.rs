@@ -1,4 +1,13 @@
-use std::{error, fmt};
+// Kept no_std-compatible so `awaitable`'s `spin` feature, which drops the
+// `std` dependency for bare-metal/embedded targets, can still use `Error`.
+//
+// `std` is on by default; `awaitable`'s manifest depends on this crate
+// with `default-features = false` and re-enables `std` through its own
+// default feature, so building `awaitable` with `--no-default-features
+// --features spin` takes the two crates `no_std` together.
+#![cfg_attr(not(feature = "std"), no_std)]
+
+use core::fmt;
 
 #[derive(Debug)]
 pub enum Error {
@@ -10,6 +19,18 @@ pub enum Error {
 
     /// Awaitable is marked done twice.
     AlreadyDone,
+
+    /// The producer was dropped (e.g. it panicked or returned early via
+    /// `?`) before calling `done`, leaving the `Awaitable` poisoned.
+    Poisoned,
+
+    /// `AwaitableMap::register` was called with a key that is already
+    /// registered and awaiting a response.
+    DuplicateKey,
+
+    /// `AwaitableMap::wake` was called with a key that is not (or is no
+    /// longer) registered.
+    UnknownKey,
 }
 
 impl fmt::Display for Error {
@@ -20,8 +41,12 @@ impl fmt::Display for Error {
             Uninitialized => "Awaitable is not initialized yet.",
             AlreadyConsumed => "Awaitable is already consumed but not yet reset.",
             AlreadyDone => "Awaitable is marked done twice.",
+            Poisoned => "The producer was dropped without calling done.",
+            DuplicateKey => "The key is already registered in the AwaitableMap.",
+            UnknownKey => "The key is not registered in the AwaitableMap.",
         })
     }
 }
 
-impl error::Error for Error {}
+#[cfg(feature = "std")]
+impl std::error::Error for Error {}
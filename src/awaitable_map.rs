@@ -0,0 +1,279 @@
+use std::{
+    collections::HashMap,
+    future::Future,
+    hash::Hash,
+    ops::Deref,
+    pin::Pin,
+    sync::{Arc, Weak},
+    task::{Context, Poll},
+};
+
+use crate::{sync::Mutex, Awaitable, Error};
+
+type Entries<K, Input, Output> = HashMap<K, Weak<RegisteredAwaitable<K, Input, Output>>>;
+
+/// A handle returned by [`AwaitableMap::register`].
+///
+/// It derefs to the underlying [`Awaitable`] so it can be polled/awaited
+/// like any other `Awaitable`, and [`RegisteredAwaitable::wait`] returns a
+/// first-class `Future` the same way [`Awaitable::wait`] does. Dropping it
+/// before the response arrives deregisters its key from the
+/// [`AwaitableMap`], so a cancelled awaiter can never leak an entry that
+/// `wake` would otherwise never find.
+///
+/// `#[must_use]` catches a bare discarded `map.register(key, input)?;`
+/// statement, but not `let _ = map.register(key, input)?;` -- that drops
+/// the handle just as immediately, deregistering `key` before a response
+/// can ever arrive, but it's rustc's own suggested fix for a `#[must_use]`
+/// warning, so the lint stays silent about it. Always bind it to a named
+/// variable, e.g. `let handle = map.register(key, input)?;`.
+#[must_use = "dropping this immediately deregisters the key from the AwaitableMap"]
+#[derive(Debug)]
+pub struct RegisteredAwaitable<K: Eq + Hash, Input, Output> {
+    key: K,
+    map: Weak<Mutex<Entries<K, Input, Output>>>,
+    awaitable: Awaitable<Input, Output>,
+}
+
+impl<K: Eq + Hash, Input, Output> Deref for RegisteredAwaitable<K, Input, Output> {
+    type Target = Awaitable<Input, Output>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.awaitable
+    }
+}
+
+impl<K: Eq + Hash, Input, Output> Drop for RegisteredAwaitable<K, Input, Output> {
+    fn drop(&mut self) {
+        let Some(map) = self.map.upgrade() else {
+            return;
+        };
+
+        let mut guard = map.lock();
+
+        if let Some(registered) = guard.get(&self.key) {
+            if std::ptr::eq(registered.as_ptr(), self) {
+                guard.remove(&self.key);
+            }
+        }
+    }
+}
+
+impl<K: Eq + Hash, Input, Output> RegisteredAwaitable<K, Input, Output> {
+    /// Return a `Future` that resolves to `take_output()` once this
+    /// registration is done.
+    ///
+    /// Mirrors [`Awaitable::wait`], but keeps the `Arc<RegisteredAwaitable>`
+    /// (not just the inner `Awaitable`) alive across polls, so `key` stays
+    /// registered in the `AwaitableMap` for as long as something is
+    /// awaiting it.
+    pub fn wait(self: Arc<Self>) -> RegisteredAwaitableFuture<K, Input, Output> {
+        RegisteredAwaitableFuture(self)
+    }
+}
+
+/// A `Future` that resolves to the output of a [`RegisteredAwaitable`].
+///
+/// Returned by [`RegisteredAwaitable::wait`].
+#[derive(Debug)]
+pub struct RegisteredAwaitableFuture<K: Eq + Hash, Input, Output>(
+    Arc<RegisteredAwaitable<K, Input, Output>>,
+);
+
+impl<K: Eq + Hash, Input, Output> Future for RegisteredAwaitableFuture<K, Input, Output> {
+    type Output = Result<Output, Error>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        match self.0.install_waker(cx.waker().clone()) {
+            Ok(true) => Poll::Ready(self.0.take_output()),
+            Ok(false) => Poll::Pending,
+            Err(err) => Poll::Ready(Err(err)),
+        }
+    }
+}
+
+/// A keyed collection of [`Awaitable`]s for transports that multiplex many
+/// in-flight requests over one connection and must route each incoming
+/// response to the correct waiter by an id.
+///
+/// Modelled after maitake-sync's `WaitMap`.
+#[derive(Debug)]
+pub struct AwaitableMap<K: Eq + Hash, Input, Output>(Arc<Mutex<Entries<K, Input, Output>>>);
+
+impl<K: Eq + Hash, Input, Output> Default for AwaitableMap<K, Input, Output> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<K: Eq + Hash, Input, Output> AwaitableMap<K, Input, Output> {
+    /// Create an empty `AwaitableMap`.
+    pub fn new() -> Self {
+        Self(Arc::new(Mutex::new(HashMap::new())))
+    }
+}
+
+impl<K: Eq + Hash + Clone, Input, Output> AwaitableMap<K, Input, Output> {
+    /// Register a fresh, reset `Awaitable` under `key`.
+    ///
+    /// Returns [`Error::DuplicateKey`] if `key` is already registered and
+    /// awaiting a response.
+    ///
+    /// The handle is a [`RegisteredAwaitable`], not a bare `Arc<Awaitable>`:
+    /// it `Deref`s to `Awaitable` so it can be polled/awaited exactly like
+    /// one, but it also has to carry enough back-reference to deregister
+    /// `key` from this map when dropped before a response arrives --
+    /// something a bare `Arc<Awaitable>` has no way to do.
+    pub fn register(
+        &self,
+        key: K,
+        input: Option<Input>,
+    ) -> Result<Arc<RegisteredAwaitable<K, Input, Output>>, Error> {
+        let mut guard = self.0.lock();
+
+        if let Some(registered) = guard.get(&key) {
+            if registered.strong_count() > 0 {
+                return Err(Error::DuplicateKey);
+            }
+        }
+
+        let awaitable = Awaitable::new();
+        awaitable.reset(input);
+
+        let registered = Arc::new(RegisteredAwaitable {
+            key: key.clone(),
+            map: Arc::downgrade(&self.0),
+            awaitable,
+        });
+
+        guard.insert(key, Arc::downgrade(&registered));
+
+        Ok(registered)
+    }
+
+    /// Look up `key`, call [`Awaitable::done`] on its entry and remove it.
+    ///
+    /// Returns [`Error::UnknownKey`] if `key` is not (or is no longer)
+    /// registered, instead of panicking, so the caller can decide whether
+    /// a stray or duplicate response is fatal.
+    pub fn wake(&self, key: &K, output: Output) -> Result<(), Error> {
+        let registered = self
+            .0
+            .lock()
+            .remove(key)
+            .and_then(|weak| weak.upgrade())
+            .ok_or(Error::UnknownKey)?;
+
+        registered.awaitable.done(output)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{task::Wake, thread};
+
+    #[test]
+    fn register_then_wake_round_trip() {
+        let map: AwaitableMap<u32, (), &'static str> = AwaitableMap::new();
+        let registered = map.register(1, None).unwrap();
+
+        map.wake(&1, "hello").unwrap();
+
+        assert_eq!(registered.take_output().unwrap(), "hello");
+    }
+
+    // No async runtime is available in this crate, so block on the future
+    // with the simplest possible executor: a waker that unparks the
+    // polling thread. Mirrors `future::tests::block_on`.
+    struct ThreadWaker(thread::Thread);
+
+    impl Wake for ThreadWaker {
+        fn wake(self: Arc<Self>) {
+            self.wake_by_ref();
+        }
+
+        fn wake_by_ref(self: &Arc<Self>) {
+            self.0.unpark();
+        }
+    }
+
+    fn block_on<F: Future>(mut future: F) -> F::Output {
+        let waker = std::task::Waker::from(Arc::new(ThreadWaker(thread::current())));
+        let mut cx = Context::from_waker(&waker);
+
+        // SAFETY: `future` is a local and is never moved again.
+        let mut future = unsafe { Pin::new_unchecked(&mut future) };
+
+        loop {
+            if let Poll::Ready(value) = future.as_mut().poll(&mut cx) {
+                return value;
+            }
+            thread::park();
+        }
+    }
+
+    #[test]
+    fn registered_wait_resolves_once_another_thread_calls_wake() {
+        let map: AwaitableMap<u32, (), &'static str> = AwaitableMap::new();
+        let registered = map.register(1, None).unwrap();
+
+        let handle = thread::spawn(move || {
+            map.wake(&1, "hello").unwrap();
+        });
+
+        assert_eq!(block_on(registered.wait()).unwrap(), "hello");
+        handle.join().unwrap();
+    }
+
+    #[test]
+    fn register_rejects_a_key_still_awaiting_a_response() {
+        let map: AwaitableMap<u32, (), &'static str> = AwaitableMap::new();
+        let _registered = map.register(1, None).unwrap();
+
+        assert!(matches!(map.register(1, None), Err(Error::DuplicateKey)));
+    }
+
+    #[test]
+    fn dropping_the_handle_deregisters_the_key() {
+        let map: AwaitableMap<u32, (), &'static str> = AwaitableMap::new();
+        let registered = map.register(1, None).unwrap();
+        drop(registered);
+
+        // The slot is gone, not just left holding a dead `Weak`, and a
+        // fresh `register` for the same key succeeds.
+        assert!(!map.0.lock().contains_key(&1));
+        assert!(map.register(1, None).is_ok());
+    }
+
+    #[test]
+    fn waking_an_unregistered_key_is_an_error_not_a_panic() {
+        let map: AwaitableMap<u32, (), &'static str> = AwaitableMap::new();
+
+        assert!(matches!(map.wake(&1, "nobody home"), Err(Error::UnknownKey)));
+    }
+
+    #[test]
+    fn waking_a_key_twice_is_unknown_key_not_a_panic() {
+        let map: AwaitableMap<u32, (), &'static str> = AwaitableMap::new();
+        let _registered = map.register(1, None).unwrap();
+
+        map.wake(&1, "first").unwrap();
+
+        assert!(matches!(map.wake(&1, "second"), Err(Error::UnknownKey)));
+    }
+
+    #[test]
+    fn dropping_the_handle_after_a_response_does_not_resurrect_the_key() {
+        let map: AwaitableMap<u32, (), &'static str> = AwaitableMap::new();
+        let registered = map.register(1, None).unwrap();
+
+        map.wake(&1, "hello").unwrap();
+
+        // `wake` already removed the entry; the handle's `Drop` must not
+        // remove a different, later registration under the same key.
+        let _fresh = map.register(1, None).unwrap();
+        drop(registered);
+        assert!(map.wake(&1, "second").is_ok());
+    }
+}
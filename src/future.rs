@@ -0,0 +1,85 @@
+use core::{
+    future::Future,
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+#[cfg(feature = "spin")]
+use alloc::sync::Arc;
+#[cfg(not(feature = "spin"))]
+use std::sync::Arc;
+
+use crate::{Awaitable, Error};
+
+/// A `Future` that resolves to the output of an [`Awaitable`].
+///
+/// Returned by [`Awaitable::wait`]. Because `install_waker` keeps a waker
+/// per caller, several tasks can each hold their own `AwaitableFuture` over
+/// the same `Arc<Awaitable>` and all of them are woken once `done` is
+/// called.
+#[derive(Debug)]
+pub struct AwaitableFuture<Input, Output>(pub(crate) Arc<Awaitable<Input, Output>>);
+
+impl<Input, Output> Future for AwaitableFuture<Input, Output> {
+    type Output = Result<Output, Error>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        match self.0.install_waker(cx.waker().clone()) {
+            Ok(true) => Poll::Ready(self.0.take_output()),
+            Ok(false) => Poll::Pending,
+            Err(err) => Poll::Ready(Err(err)),
+        }
+    }
+}
+
+#[cfg(all(test, not(feature = "spin")))]
+mod tests {
+    use super::*;
+    use std::task::Wake;
+    use std::thread;
+
+    // No async runtime is available in this crate, so block on the future
+    // with the simplest possible executor: a waker that unparks the
+    // polling thread, matching the `Future::poll` wake-up contract without
+    // pulling in an external dependency.
+    struct ThreadWaker(thread::Thread);
+
+    impl Wake for ThreadWaker {
+        fn wake(self: Arc<Self>) {
+            self.wake_by_ref();
+        }
+
+        fn wake_by_ref(self: &Arc<Self>) {
+            self.0.unpark();
+        }
+    }
+
+    fn block_on<F: Future>(mut future: F) -> F::Output {
+        let waker = std::task::Waker::from(Arc::new(ThreadWaker(thread::current())));
+        let mut cx = Context::from_waker(&waker);
+
+        // SAFETY: `future` is a local and is never moved again.
+        let mut future = unsafe { Pin::new_unchecked(&mut future) };
+
+        loop {
+            if let Poll::Ready(value) = future.as_mut().poll(&mut cx) {
+                return value;
+            }
+            thread::park();
+        }
+    }
+
+    #[test]
+    fn wait_resolves_once_another_thread_calls_done() {
+        let awaitable = Arc::new(Awaitable::<(), u32>::new());
+        awaitable.reset(None);
+
+        let producer = awaitable.clone();
+        let handle = thread::spawn(move || {
+            producer.done(42).unwrap();
+        });
+
+        assert_eq!(block_on(awaitable.wait()).unwrap(), 42);
+        handle.join().unwrap();
+    }
+}
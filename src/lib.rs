@@ -1,46 +1,52 @@
 #![cfg_attr(docsrs, feature(doc_auto_cfg))]
+// `spin` replaces the std-backed mutex with a spinlock that works without an
+// OS, so the crate itself can drop its dependency on `std` under that
+// feature and be placed in a `static` on bare-metal/embedded targets.
+#![cfg_attr(feature = "spin", no_std)]
 
-use cfg_if::cfg_if;
-use std::{mem, task::Waker};
+#[cfg(feature = "spin")]
+extern crate alloc;
 
-cfg_if! {
-    if #[cfg(feature = "parking_lot")] {
-        use parking_lot::{const_mutex, Mutex};
-    } else {
-        use std::sync::{Mutex as StdMutex, MutexGuard};
+// `AwaitableMap` is built on `std::collections::HashMap`, which isn't
+// available under `alloc`-only `no_std`, so it is std-only for now.
+#[cfg(not(feature = "spin"))]
+mod awaitable_map;
+mod future;
+mod sync;
 
-        #[derive(Debug)]
-        #[repr(transparent)]
-        struct Mutex<T>(StdMutex<T>);
+#[cfg(feature = "spin")]
+use alloc::{sync::Arc, vec::Vec};
+use core::{mem, task::Waker};
+#[cfg(not(feature = "spin"))]
+use std::sync::Arc;
 
-        impl<T> Mutex<T> {
-            fn new(val: T) -> Self {
-                Self(StdMutex::new(val))
-            }
-
-            #[track_caller]
-            fn lock(&self) -> MutexGuard<'_, T> {
-                self.0.lock().unwrap()
-            }
-        }
-    }
-}
+use sync::Mutex;
 
 pub use awaitable_error::Error;
+#[cfg(not(feature = "spin"))]
+pub use awaitable_map::AwaitableMap;
+pub use future::AwaitableFuture;
 
 #[derive(Debug)]
 enum InnerState<Input, Output> {
     Uninitialized,
 
-    Ongoing(Option<Input>, Option<Waker>),
+    Ongoing(Option<Input>, Vec<Waker>),
 
     /// The awaitable is done
     Done(Output),
 
     Consumed,
+
+    /// The producer was dropped (e.g. it panicked or returned early via
+    /// `?`) before calling `done`.
+    Poisoned,
 }
 
-/// Awaitable guarantees that there is no spurious wakeup
+/// Awaitable guarantees that there is no spurious wakeup.
+///
+/// Any number of tasks can call `install_waker` on the same `Awaitable`
+/// while it is `Ongoing`: every one of them is woken once `done` is called.
 #[derive(Debug)]
 pub struct Awaitable<Input, Output>(Mutex<InnerState<Input, Output>>);
 
@@ -61,9 +67,13 @@ impl<Input, Output> Awaitable<Input, Output> {
     /// Create an uninitialized `Awaitable`.
     ///
     /// Must be `reset` before it can be used.
-    #[cfg(feature = "parking_lot")]
+    ///
+    /// Available with the `parking_lot` and `spin` lock backends, so that
+    /// e.g. a `spin`-backed `Awaitable` can be placed in a `static`; the
+    /// default std-backed lock has no `const_new`.
+    #[cfg(any(feature = "parking_lot", feature = "spin"))]
     pub const fn const_new() -> Self {
-        Self(const_mutex(InnerState::Uninitialized))
+        Self(sync::const_mutex(InnerState::Uninitialized))
     }
 }
 
@@ -73,11 +83,53 @@ impl<Input, Output> Awaitable<Input, Output> {
     /// After this call, `install_waker`, `take_input` and `done`
     /// can be called.
     pub fn reset(&self, input: Option<Input>) {
-        *self.0.lock() = InnerState::Ongoing(input, None);
+        *self.0.lock() = InnerState::Ongoing(input, Vec::new());
+    }
+
+    /// Reset `Awaitable` to its initial state, like `reset`, and return a
+    /// guard that poisons it if dropped before `done` is called.
+    ///
+    /// Use this instead of bare `reset` whenever the producer's path to
+    /// `done` can panic or return early, so awaiters observe a clean
+    /// `Error::Poisoned` instead of hanging forever.
+    ///
+    /// The `#[must_use]` below only fires for a bare, fully discarded
+    /// `awaitable.begin(input);` statement. It does *not* fire for `let _ =
+    /// awaitable.begin(input);` -- that drops the guard just as immediately,
+    /// poisoning the `Awaitable` before the producer does any work, but
+    /// `let _ = ...` is rustc's own suggested fix for silencing a
+    /// `#[must_use]` warning, so the lint can't flag it. Always bind the
+    /// guard to a named variable, e.g. `let _guard = awaitable.begin(input);`.
+    #[must_use = "discarding this guard poisons the Awaitable immediately"]
+    pub fn begin(&self, input: Option<Input>) -> ProducerGuard<'_, Input, Output> {
+        self.reset(input);
+        ProducerGuard(self)
+    }
+
+    /// Clear a `Poisoned` state, recovering the `Awaitable` for reuse.
+    ///
+    /// Unlike `reset`, this does not start a new operation: it only
+    /// clears `Poisoned` back to `Uninitialized`, mirroring
+    /// `std::sync::Mutex::clear_poison`.
+    pub fn clear_poison(&self) {
+        let mut guard = self.0.lock();
+
+        if matches!(&*guard, InnerState::Poisoned) {
+            *guard = InnerState::Uninitialized;
+        }
+    }
+
+    /// Return true if current state is `Poisoned`.
+    pub fn is_poisoned(&self) -> bool {
+        matches!(&*self.0.lock(), InnerState::Poisoned)
     }
 
     /// Return true if the task is already done.
     ///
+    /// Multiple tasks can await the same `Awaitable`: each call to
+    /// `install_waker` registers (or, if it belongs to the same task,
+    /// updates in place) a waker, and `done` wakes all of them.
+    ///
     /// **
     /// `install_waker` must not be called after `take_output` is called.
     /// **
@@ -89,12 +141,19 @@ impl<Input, Output> Awaitable<Input, Output> {
         match &mut *guard {
             Uninitialized => Err(Error::Uninitialized),
 
-            Ongoing(_input, stored_waker) => {
-                *stored_waker = Some(waker);
+            Ongoing(_input, wakers) => {
+                if let Some(stored_waker) =
+                    wakers.iter_mut().find(|stored| stored.will_wake(&waker))
+                {
+                    *stored_waker = waker;
+                } else {
+                    wakers.push(waker);
+                }
                 Ok(false)
             }
             Done(_) => Ok(true),
             Consumed => Err(Error::AlreadyConsumed),
+            Poisoned => Err(Error::Poisoned),
         }
     }
 
@@ -110,6 +169,7 @@ impl<Input, Output> Awaitable<Input, Output> {
             Ongoing(input, _stored_waker) => Ok(input.take()),
             Done(_) => Ok(None),
             Consumed => Err(Error::AlreadyConsumed),
+            Poisoned => Err(Error::Poisoned),
         }
     }
 
@@ -125,26 +185,39 @@ impl<Input, Output> Awaitable<Input, Output> {
             Uninitialized => Err(Error::Uninitialized),
 
             Done(_) => Err(Error::AlreadyDone),
-            Ongoing(_input, stored_waker) => {
-                if let Some(waker) = stored_waker {
+            Ongoing(_input, wakers) => {
+                for waker in wakers {
                     waker.wake();
                 }
 
                 Ok(())
             }
             Consumed => Err(Error::AlreadyConsumed),
+            Poisoned => Err(Error::Poisoned),
         }
     }
 
-    /// Return `Some(output)` if the awaitable is done.
-    pub fn take_output(&self) -> Option<Output> {
+    /// Return the output if the awaitable is done, or the error that
+    /// prevented it from ever completing (including `Error::Poisoned` if
+    /// the producer was dropped without calling `done`).
+    ///
+    /// Must only be called once `is_done()` or `is_poisoned()` returns
+    /// true.
+    pub fn take_output(&self) -> Result<Output, Error> {
         use InnerState::*;
 
-        let prev_state = mem::replace(&mut *self.0.lock(), Consumed);
+        let mut guard = self.0.lock();
 
-        match prev_state {
-            Done(value) => Some(value),
-            _ => None,
+        match &*guard {
+            Done(_) | Poisoned => {}
+            Uninitialized | Ongoing(..) => return Err(Error::Uninitialized),
+            Consumed => return Err(Error::AlreadyConsumed),
+        }
+
+        match mem::replace(&mut *guard, Consumed) {
+            Done(value) => Ok(value),
+            Poisoned => Err(Error::Poisoned),
+            _ => unreachable!(),
         }
     }
 
@@ -157,4 +230,133 @@ impl<Input, Output> Awaitable<Input, Output> {
     pub fn is_consumed(&self) -> bool {
         matches!(&*self.0.lock(), InnerState::Consumed)
     }
+
+    /// Return a `Future` that resolves to `take_output()` once this
+    /// `Awaitable` is done, instead of hand-rolling a poll loop around
+    /// `install_waker`/`take_output`.
+    ///
+    /// `self` must be an `Arc` so the `Future` can keep the `Awaitable`
+    /// alive across polls; combined with the ability to install more than
+    /// one waker, several tasks can each call `wait` on the same
+    /// `Arc<Awaitable>` and `.await` it safely.
+    pub fn wait(self: Arc<Self>) -> AwaitableFuture<Input, Output> {
+        AwaitableFuture(self)
+    }
+}
+
+/// RAII guard returned by [`Awaitable::begin`].
+///
+/// If dropped while the `Awaitable` is still `Ongoing` -- i.e. `done` was
+/// never called, typically because the producer task panicked or returned
+/// early -- it transitions the `Awaitable` to `Poisoned` and wakes every
+/// stored waker, so awaiters observe `Error::Poisoned` instead of hanging
+/// forever.
+///
+/// `#[must_use]` catches a bare discarded `awaitable.begin(input);`
+/// statement, but not `let _ = awaitable.begin(input);` -- that drops the
+/// guard just as immediately, poisoning the `Awaitable` before the
+/// producer does any work, but it's rustc's own suggested fix for a
+/// `#[must_use]` warning, so the lint stays silent about it. Always bind
+/// the guard to a named variable, e.g. `let _guard =
+/// awaitable.begin(input);`.
+#[must_use = "discarding this guard poisons the Awaitable immediately"]
+#[derive(Debug)]
+pub struct ProducerGuard<'a, Input, Output>(&'a Awaitable<Input, Output>);
+
+impl<Input, Output> Drop for ProducerGuard<'_, Input, Output> {
+    fn drop(&mut self) {
+        use InnerState::*;
+
+        let mut guard = self.0 .0.lock();
+
+        if let Ongoing(..) = &*guard {
+            if let Ongoing(_input, wakers) = mem::replace(&mut *guard, Poisoned) {
+                for waker in wakers {
+                    waker.wake();
+                }
+            }
+        }
+    }
+}
+
+#[cfg(all(test, not(feature = "spin")))]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::task::Wake;
+
+    struct CountingWaker(AtomicUsize);
+
+    impl Wake for CountingWaker {
+        fn wake(self: Arc<Self>) {
+            self.wake_by_ref();
+        }
+
+        fn wake_by_ref(self: &Arc<Self>) {
+            self.0.fetch_add(1, Ordering::SeqCst);
+        }
+    }
+
+    #[test]
+    fn done_wakes_every_installed_waker() {
+        let awaitable: Awaitable<(), u32> = Awaitable::new();
+        awaitable.reset(None);
+
+        let w1 = Arc::new(CountingWaker(AtomicUsize::new(0)));
+        let w2 = Arc::new(CountingWaker(AtomicUsize::new(0)));
+
+        assert!(!awaitable.install_waker(Waker::from(w1.clone())).unwrap());
+        assert!(!awaitable.install_waker(Waker::from(w2.clone())).unwrap());
+
+        awaitable.done(42).unwrap();
+
+        assert_eq!(w1.0.load(Ordering::SeqCst), 1);
+        assert_eq!(w2.0.load(Ordering::SeqCst), 1);
+        assert_eq!(awaitable.take_output().unwrap(), 42);
+    }
+
+    #[test]
+    fn dropping_the_guard_without_done_poisons_and_wakes() {
+        let awaitable: Awaitable<(), u32> = Awaitable::new();
+        let waker = Arc::new(CountingWaker(AtomicUsize::new(0)));
+
+        {
+            let guard = awaitable.begin(None);
+            assert!(!awaitable.install_waker(Waker::from(waker.clone())).unwrap());
+            drop(guard);
+        }
+
+        assert!(awaitable.is_poisoned());
+        assert_eq!(waker.0.load(Ordering::SeqCst), 1);
+        assert!(matches!(awaitable.take_output(), Err(Error::Poisoned)));
+    }
+
+    #[test]
+    fn clear_poison_recovers_the_slot_for_reuse() {
+        let awaitable: Awaitable<u32, u32> = Awaitable::new();
+        {
+            let _guard = awaitable.begin(None);
+        }
+        assert!(awaitable.is_poisoned());
+
+        awaitable.clear_poison();
+        assert!(!awaitable.is_poisoned());
+
+        awaitable.reset(Some(5));
+        assert_eq!(awaitable.take_input().unwrap(), Some(5));
+        awaitable.done(7).unwrap();
+        assert_eq!(awaitable.take_output().unwrap(), 7);
+    }
+
+    #[test]
+    fn done_after_begin_does_not_poison() {
+        let awaitable: Awaitable<(), u32> = Awaitable::new();
+        {
+            let _guard = awaitable.begin(None);
+            awaitable.done(1).unwrap();
+        }
+
+        assert!(!awaitable.is_poisoned());
+        assert_eq!(awaitable.take_output().unwrap(), 1);
+    }
 }
@@ -0,0 +1,34 @@
+// `parking_lot` and `spin` are optional dependencies, each gating a
+// same-named feature declared in `Cargo.toml`; `awaitable-error`'s `std`
+// feature is on by default and dropped (via `--no-default-features
+// --features spin`) together with this crate's own `std` dependency.
+use cfg_if::cfg_if;
+
+cfg_if! {
+    if #[cfg(feature = "parking_lot")] {
+        pub(crate) use parking_lot::{const_mutex, Mutex};
+    } else if #[cfg(feature = "spin")] {
+        pub(crate) use spin::Mutex;
+
+        pub(crate) const fn const_mutex<T>(val: T) -> Mutex<T> {
+            Mutex::new(val)
+        }
+    } else {
+        use std::sync::{Mutex as StdMutex, MutexGuard};
+
+        #[derive(Debug)]
+        #[repr(transparent)]
+        pub(crate) struct Mutex<T>(StdMutex<T>);
+
+        impl<T> Mutex<T> {
+            pub(crate) fn new(val: T) -> Self {
+                Self(StdMutex::new(val))
+            }
+
+            #[track_caller]
+            pub(crate) fn lock(&self) -> MutexGuard<'_, T> {
+                self.0.lock().unwrap()
+            }
+        }
+    }
+}